@@ -1,5 +1,9 @@
 use framebuffer::Framebuffer;
 
+pub const DEFAULT_BAND_COUNT: usize = 128;
+pub const DEFAULT_MIN_FREQ: f32 = 20.0;
+pub const DEFAULT_MAX_FREQ: f32 = 20_000.0;
+
 pub struct FbPainter {
     fb: Framebuffer,
     w: usize,
@@ -7,6 +11,9 @@ pub struct FbPainter {
     line_length: usize,
     bytespp: usize,
     frame: Vec<u8>,
+    band_count: usize,
+    min_freq: f32,
+    max_freq: f32,
 }
 
 impl FbPainter {
@@ -26,30 +33,70 @@ impl FbPainter {
             line_length: line_length,
             bytespp: bytespp,
             frame: frame,
+            band_count: DEFAULT_BAND_COUNT,
+            min_freq: DEFAULT_MIN_FREQ,
+            max_freq: DEFAULT_MAX_FREQ,
         }
     }
 
-    pub fn update(&mut self, buffer: &[f32]) {
-        let bins = buffer.len();
-        let bin_width = self.w as f32 / bins as f32;
+    //Lets the caller tune how many log-spaced bands are drawn and which frequency range they cover.
+    pub fn set_bands(&mut self, band_count: usize, min_freq: f32, max_freq: f32) {
+        self.band_count = band_count;
+        self.min_freq = min_freq;
+        self.max_freq = max_freq;
+    }
+
+    //`buffer` holds the magnitude of FFT bins `0 .. n/2` for a transform of length `n`,
+    //sampled at `sample_rate`. Bins are grouped into logarithmically spaced bands so bass
+    //and treble get comparable screen space, and each bar's color reflects its magnitude.
+    pub fn update(&mut self, buffer: &[f32], sample_rate: u32) {
+        let fft_len = buffer.len() * 2;
+        let bin_width = self.w as f32 / self.band_count as f32;
+        let freq_ratio = self.max_freq / self.min_freq;
+
+        for band in 0 .. self.band_count {
+            let freq_lo = self.min_freq * freq_ratio.powf(band as f32 / self.band_count as f32);
+            let freq_hi = self.min_freq * freq_ratio.powf((band + 1) as f32 / self.band_count as f32);
+
+            let bin_lo = freq_to_bin(freq_lo, fft_len, sample_rate, buffer.len());
+            let bin_hi = freq_to_bin(freq_hi, fft_len, sample_rate, buffer.len()).max(bin_lo + 1);
+
+            let magnitude = buffer[bin_lo .. bin_hi].iter().cloned().fold(0.0f32, f32::max)
+                .max(0.0).min(1.0);
 
-        for (i, magnitude) in buffer.iter().enumerate() {
-            let x_start = (i as f32 * bin_width) as usize;
-            let x_stop = ((i + 1) as f32 * bin_width) as usize;
+            let x_start = (band as f32 * bin_width) as usize;
+            let x_stop = ((band + 1) as f32 * bin_width) as usize;
             let y_stop = (self.h as f32 * magnitude) as usize;
+            let (r, g, b) = magnitude_to_color(magnitude);
 
             for y in 0 .. y_stop {
                 for x in x_start .. x_stop {
                     let curr_index = y * self.line_length + x * self.bytespp;
-                    self.frame[curr_index] = 255;
-                    self.frame[curr_index + 1] = 255;
-                    self.frame[curr_index + 2] = 255;
+                    self.frame[curr_index] = r;
+                    self.frame[curr_index + 1] = g;
+                    self.frame[curr_index + 2] = b;
                 }
             }
         }
 
-        self.frame.iter_mut().map(|byte| 255);
-        println!("{}", self.frame[0]);
         let _ = self.fb.write_frame(&self.frame);
     }
 }
+
+fn freq_to_bin(freq: f32, fft_len: usize, sample_rate: u32, bin_count: usize) -> usize {
+    let bin = (freq * fft_len as f32 / sample_rate as f32) as usize;
+    bin.min(bin_count.saturating_sub(1))
+}
+
+//Blue (quiet) -> green -> red (loud) gradient written into all three byte channels.
+fn magnitude_to_color(magnitude: f32) -> (u8, u8, u8) {
+    let m = magnitude.max(0.0).min(1.0);
+
+    if m < 0.5 {
+        let t = m / 0.5;
+        (0, (t * 255.0) as u8, ((1.0 - t) * 255.0) as u8)
+    } else {
+        let t = (m - 0.5) / 0.5;
+        ((t * 255.0) as u8, ((1.0 - t) * 255.0) as u8, 0)
+    }
+}
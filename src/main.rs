@@ -4,23 +4,48 @@ extern crate framebuffer;
 
 mod wav_loader;
 mod fb;
+mod resample;
+mod mixer;
 
 use std::env;
 use std::{u8, i16, i32};
 use std::f32::consts::PI;
 use std::io::Write;
 use std::ffi::CString;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use alsa::{Direction, ValueOr};
 use alsa::pcm::{PCM, HwParams, Format, Access};
 
 use wav_loader::{Wav, WavData};
 use fb::FbPainter;
+use resample::{InterpolationMode, Resampler};
+use mixer::{deinterleave, interleave, downmix, default_downmix_matrix};
 
 fn main() {
     let mut args = env::args();
     assert!(args.len() > 1);
     let path_to_audio = args.nth(1).unwrap();
+    let interpolation_mode = args.next()
+        .and_then(|s| InterpolationMode::from_str(&s))
+        .unwrap_or(InterpolationMode::Linear);
+    let window = args.next()
+        .and_then(|s| Window::from_str(&s))
+        .unwrap_or(Window::Hann);
+    let magnitude_scale = args.next()
+        .and_then(|s| MagnitudeScale::from_str(&s))
+        .unwrap_or(MagnitudeScale::Decibel);
+    let band_count = args.next()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(fb::DEFAULT_BAND_COUNT);
+    let min_freq = args.next()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(fb::DEFAULT_MIN_FREQ);
+    let max_freq = args.next()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(fb::DEFAULT_MAX_FREQ);
 
     //Sound card settings
     let pcm = PCM::open(&*CString::new("default").unwrap(), Direction::Playback, false).unwrap();
@@ -30,7 +55,10 @@ fn main() {
     let samples = match wav.data {
         WavData::U8(ref data) => u8_to_floats(&data),
         WavData::I16(ref data) => i16_to_floats(&data),
+        WavData::I24(ref data) => i24_to_floats(&data),
         WavData::I32(ref data) => i32_to_floats(&data),
+        WavData::F32(ref data) => f32_to_floats(&data),
+        WavData::F64(ref data) => f64_to_floats(&data),
         _ => panic!("Unhandled audio format"),
     };
 
@@ -40,18 +68,118 @@ fn main() {
     hwp.set_access(Access::RWInterleaved).unwrap();
     pcm.hw_params(&hwp).unwrap();
 
+    //The card may not support the file's exact rate, so resample to whatever was negotiated.
+    //Resampling walks a fractional position through the signal, so it has to run on each
+    //channel's own plane; doing it on the raw interleaved stream would interpolate across
+    //channel boundaries and destroy frame alignment.
+    let device_rate = hwp.get_rate().unwrap();
+    let channels = wav.num_channels as usize;
+    let resampler = Resampler::new(interpolation_mode);
+    let resampled_planes: Vec<Vec<f32>> = deinterleave(&samples, channels).iter()
+        .map(|plane| resampler.resample(plane, wav.sample_rate, device_rate))
+        .collect();
+    let samples = interleave(&resampled_planes);
+
+    let downmix_matrix = default_downmix_matrix(channels);
+
     let mut painter = FbPainter::new();
+    painter.set_bands(band_count, min_freq, max_freq);
     let mut io = pcm.io_f32().unwrap();
-    for chunk in samples.chunks(512) {
+    for chunk in samples.chunks(512 * channels) {
         io.writei(chunk);
-        let (c, s) = fft(chunk, chunk.len());
+
+        let planes = deinterleave(chunk, channels);
+        let mono = downmix(&planes, &downmix_matrix);
+
+        let mut windowed: Vec<f32> = mono.iter().enumerate()
+            .map(|(i, &sample)| sample * window.coefficient(i, mono.len()))
+            .collect();
+        let correction = window.correction_factor(windowed.len());
+
+        //The final chunk of a file is rarely an exact multiple of the chunk size, so its
+        //length is usually not a power of two; zero-pad it out to one rather than feeding
+        //fft a length it can't bit-reverse-permute.
+        let fft_len = windowed.len().next_power_of_two();
+        windowed.resize(fft_len, 0.0);
+
+        let (c, s) = fft(&windowed, fft_len);
 
         //Only use the real part, since imaginary is a duplicate
         let mut magnitudes = Vec::new();
-        for i in 0 .. c.len() / 2 { 
-            magnitudes.push((c[i].powi(2) + s[i].powi(2)).sqrt() / (c.len() as f32 / 2.0));
+        for i in 0 .. c.len() / 2 {
+            let magnitude = (c[i].powi(2) + s[i].powi(2)).sqrt() / (c.len() as f32 / 2.0) * correction;
+            magnitudes.push(magnitude_scale.apply(magnitude));
+        }
+        painter.update(&magnitudes, device_rate);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    fn from_str(s: &str) -> Option<Window> {
+        match s {
+            "rectangular" => Some(Window::Rectangular),
+            "hann" => Some(Window::Hann),
+            "hamming" => Some(Window::Hamming),
+            "blackman" => Some(Window::Blackman),
+            _ => None,
+        }
+    }
+
+    fn coefficient(&self, index: usize, total: usize) -> f32 {
+        match *self {
+            Window::Rectangular => 1.0,
+            Window::Hann => hanning_window(index, total),
+            Window::Hamming => {
+                0.54 - 0.46 * ((2.0 * PI * index as f32) / (total as f32 - 1.0)).cos()
+            },
+            Window::Blackman => {
+                let a = (2.0 * PI * index as f32) / (total as f32 - 1.0);
+                let b = (4.0 * PI * index as f32) / (total as f32 - 1.0);
+                0.42 - 0.5 * a.cos() + 0.08 * b.cos()
+            },
+        }
+    }
+
+    //Coherent gain correction so windowed magnitudes stay comparable to a rectangular window.
+    fn correction_factor(&self, total: usize) -> f32 {
+        let sum: f32 = (0 .. total).map(|i| self.coefficient(i, total)).sum();
+        total as f32 / sum
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MagnitudeScale {
+    Linear,
+    Decibel,
+}
+
+impl MagnitudeScale {
+    fn from_str(s: &str) -> Option<MagnitudeScale> {
+        match s {
+            "linear" => Some(MagnitudeScale::Linear),
+            "decibel" | "db" => Some(MagnitudeScale::Decibel),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, magnitude: f32) -> f32 {
+        const DB_FLOOR: f32 = -90.0;
+
+        match *self {
+            MagnitudeScale::Linear => magnitude,
+            MagnitudeScale::Decibel => {
+                let db = (20.0 * magnitude.max(1e-9).log10()).max(DB_FLOOR);
+                (db - DB_FLOOR) / -DB_FLOOR
+            },
         }
-        painter.update(&magnitudes);
     }
 }
 
@@ -59,38 +187,76 @@ fn hanning_window(index: usize, total: usize) -> f32 {
     0.5 * (1.0 - ((2.0 * index as f32 * PI) / (total as f32 - 1.0)).cos())
 }
 
+thread_local! {
+    //Twiddle factors only depend on the transform length, so cache them per `n`
+    //instead of recomputing cos/sin on every 512-sample chunk. Cached as an `Rc`
+    //so fetching them is a refcount bump, not a copy of the whole table.
+    static TWIDDLE_CACHE: RefCell<HashMap<usize, Rc<Vec<(f32, f32)>>>> = RefCell::new(HashMap::new());
+}
+
+fn twiddle_factors(n: usize) -> Rc<Vec<(f32, f32)>> {
+    TWIDDLE_CACHE.with(|cache| {
+        cache.borrow_mut().entry(n).or_insert_with(|| {
+            Rc::new((0 .. n / 2).map(|k| {
+                let angle = -2.0 * PI * (k as f32 / n as f32);
+                (angle.cos(), angle.sin())
+            }).collect())
+        }).clone()
+    })
+}
+
+fn bit_reverse(mut value: usize, bits: u32) -> usize {
+    let mut reversed = 0;
+    for _ in 0 .. bits {
+        reversed = (reversed << 1) | (value & 1);
+        value >>= 1;
+    }
+    reversed
+}
+
+//Iterative in-place Cooley-Tukey radix-2 FFT. `len` must be a power of two.
 fn fft(samples: &[f32], len: usize) -> (Vec<f32>, Vec<f32>) {
-    if len == 1 {
-        (vec![samples[0]], vec![0.0])
-    } else {
-        let mut output_re = vec![0.0; len];
-        let mut output_im = vec![0.0; len];
-
-        let mut e_samples = Vec::new();
-        let mut o_samples = Vec::new();
-
-        for i in 0 .. len {
-            if i % 2 == 0 {
-                e_samples.push(samples[i]);
-            } else {
-                o_samples.push(samples[i]);
-            }
-        }
+    debug_assert!(len.is_power_of_two(), "fft length must be a power of two, got {}", len);
+
+    let bits = (len.trailing_zeros()) as u32;
+    let mut re = vec![0.0; len];
+    let mut im = vec![0.0; len];
+    for i in 0 .. len {
+        re[bit_reverse(i, bits)] = samples[i];
+    }
+
+    let twiddles = twiddle_factors(len);
+
+    let mut size = 2;
+    while size <= len {
+        let half = size / 2;
+        let stride = len / size;
 
-        let (even_re, even_im) = fft(&e_samples, len / 2);
-        let (uneven_re, uneven_im) = fft(&o_samples, len / 2);
+        let mut start = 0;
+        while start < len {
+            for k in 0 .. half {
+                let (tw_re, tw_im) = twiddles[k * stride];
 
-        for i in 0 .. len / 2 {
-            let val = -2.0 * PI * (i as f32 / len as f32);
+                let a_re = re[start + k];
+                let a_im = im[start + k];
+                let b_re = re[start + k + half];
+                let b_im = im[start + k + half];
 
-            output_re[i] = even_re[i] + val.cos() * uneven_re[i] - val.sin() * uneven_im[i];
-            output_im[i] = even_im[i] + val.cos() * uneven_im[i] + val.sin() * uneven_re[i];
+                let t_re = b_re * tw_re - b_im * tw_im;
+                let t_im = b_re * tw_im + b_im * tw_re;
 
-            output_re[i + len / 2] = even_re[i] - val.cos() * uneven_re[i] + val.sin() * uneven_im[i];
-            output_im[i + len / 2] = even_im[i] - val.cos() * uneven_im[i] - val.sin() * uneven_re[i];
+                re[start + k] = a_re + t_re;
+                im[start + k] = a_im + t_im;
+                re[start + k + half] = a_re - t_re;
+                im[start + k + half] = a_im - t_im;
+            }
+            start += size;
         }
-        (output_re, output_im)
+
+        size *= 2;
     }
+
+    (re, im)
 }
 
 fn dft(samples: &[f32], bin_count: usize, sample_rate: u32) {
@@ -132,3 +298,17 @@ fn i16_to_floats(src: &[i16]) -> Vec<f32> {
 fn i32_to_floats(src: &[i32]) -> Vec<f32> {
     src.iter().map(|&sample| sample as f32 / i32::MAX as f32).collect()
 }
+
+//24-bit samples are already sign-extended into i32, but their range only spans 23 bits
+fn i24_to_floats(src: &[i32]) -> Vec<f32> {
+    const I24_MAX: f32 = 8_388_607.0;
+    src.iter().map(|&sample| sample as f32 / I24_MAX).collect()
+}
+
+fn f32_to_floats(src: &[f32]) -> Vec<f32> {
+    src.to_vec()
+}
+
+fn f64_to_floats(src: &[f64]) -> Vec<f32> {
+    src.iter().map(|&sample| sample as f32).collect()
+}
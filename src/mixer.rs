@@ -0,0 +1,78 @@
+//Splits interleaved multichannel frames into per-channel planes and collapses
+//them into a single mono signal for analysis, without touching the interleaved
+//buffer used for playback.
+
+const SQRT_1_2: f32 = 0.70710678;
+
+//Deinterleaves `samples` (channel-major per frame) into one `Vec<f32>` per channel.
+pub fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    assert!(channels > 0, "channel count must be positive");
+
+    let frames = samples.len() / channels;
+    let mut planes = vec![Vec::with_capacity(frames); channels];
+
+    for frame in samples.chunks(channels) {
+        for (channel, &sample) in frame.iter().enumerate() {
+            planes[channel].push(sample);
+        }
+    }
+
+    planes
+}
+
+//Re-interleaves one `Vec<f32>` per channel back into channel-major frames.
+//Inverse of `deinterleave`; all planes are expected to be the same length.
+pub fn interleave(planes: &[Vec<f32>]) -> Vec<f32> {
+    if planes.is_empty() {
+        return Vec::new();
+    }
+
+    let frames = planes[0].len();
+    let channels = planes.len();
+    let mut samples = Vec::with_capacity(frames * channels);
+
+    for frame in 0 .. frames {
+        for plane in planes.iter() {
+            samples.push(plane[frame]);
+        }
+    }
+
+    samples
+}
+
+//Default downmix weights for a given channel count: a plain average for
+//anything we don't special-case, and the standard ITU front/center/surround
+//coefficients for 5.1 (order: FL, FR, C, LFE, SL, SR).
+pub fn default_downmix_matrix(channels: usize) -> Vec<f32> {
+    match channels {
+        6 => vec![1.0, 1.0, SQRT_1_2, 0.0, SQRT_1_2, SQRT_1_2],
+        n => vec![1.0; n],
+    }
+}
+
+//Combines per-channel planes into a single mono signal using `weights`,
+//normalized so the result stays roughly within -1.0..1.0.
+pub fn downmix(planes: &[Vec<f32>], weights: &[f32]) -> Vec<f32> {
+    assert_eq!(planes.len(), weights.len(), "one weight is required per channel");
+
+    if planes.is_empty() {
+        return Vec::new();
+    }
+
+    let frames = planes[0].len();
+    let weight_sum: f32 = weights.iter().sum();
+    let norm = if weight_sum.abs() > 1e-7 { weight_sum } else { 1.0 };
+
+    let mut mono = vec![0.0f32; frames];
+    for (plane, &weight) in planes.iter().zip(weights.iter()) {
+        for (out, &sample) in mono.iter_mut().zip(plane.iter()) {
+            *out += sample * weight;
+        }
+    }
+
+    for sample in mono.iter_mut() {
+        *sample /= norm;
+    }
+
+    mono
+}
@@ -0,0 +1,159 @@
+use std::f32::consts::PI;
+
+const POLYPHASE_PHASES: usize = 32;
+const POLYPHASE_TAPS_PER_PHASE: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub enum InterpolationMode {
+    Nearest,
+    Linear,
+    Cosine,
+    Cubic,
+    Polyphase,
+}
+
+impl InterpolationMode {
+    pub fn from_str(s: &str) -> Option<InterpolationMode> {
+        match s {
+            "nearest" => Some(InterpolationMode::Nearest),
+            "linear" => Some(InterpolationMode::Linear),
+            "cosine" => Some(InterpolationMode::Cosine),
+            "cubic" => Some(InterpolationMode::Cubic),
+            "polyphase" => Some(InterpolationMode::Polyphase),
+            _ => None,
+        }
+    }
+}
+
+pub struct Resampler {
+    mode: InterpolationMode,
+    filter_bank: Option<Vec<Vec<f32>>>,
+}
+
+impl Resampler {
+    pub fn new(mode: InterpolationMode) -> Resampler {
+        let filter_bank = match mode {
+            InterpolationMode::Polyphase => Some(build_polyphase_filter_bank(POLYPHASE_PHASES, POLYPHASE_TAPS_PER_PHASE)),
+            _ => None,
+        };
+
+        Resampler {
+            mode: mode,
+            filter_bank: filter_bank,
+        }
+    }
+
+    //Converts `input`, sampled at `in_rate`, to the equivalent stream at `out_rate`.
+    pub fn resample(&self, input: &[f32], in_rate: u32, out_rate: u32) -> Vec<f32> {
+        if in_rate == out_rate || input.is_empty() {
+            return input.to_vec();
+        }
+
+        let ratio = out_rate as f32 / in_rate as f32;
+        let out_len = ((input.len() as f32) * ratio).round() as usize;
+        let mut output = Vec::with_capacity(out_len);
+
+        let mut pos = 0.0f32;
+        let step = 1.0 / ratio;
+        for _ in 0 .. out_len {
+            output.push(self.sample_at(input, pos));
+            pos += step;
+        }
+
+        output
+    }
+
+    fn sample_at(&self, input: &[f32], pos: f32) -> f32 {
+        match self.mode {
+            InterpolationMode::Nearest => sample_nearest(input, pos),
+            InterpolationMode::Linear => sample_linear(input, pos),
+            InterpolationMode::Cosine => sample_cosine(input, pos),
+            InterpolationMode::Cubic => sample_cubic(input, pos),
+            InterpolationMode::Polyphase => {
+                let bank = self.filter_bank.as_ref().expect("polyphase filter bank was not built");
+                sample_polyphase(input, pos, bank)
+            },
+        }
+    }
+}
+
+fn at(input: &[f32], index: isize) -> f32 {
+    if index < 0 || index as usize >= input.len() {
+        0.0
+    } else {
+        input[index as usize]
+    }
+}
+
+fn sample_nearest(input: &[f32], pos: f32) -> f32 {
+    at(input, pos.round() as isize)
+}
+
+fn sample_linear(input: &[f32], pos: f32) -> f32 {
+    let base = pos.floor() as isize;
+    let t = pos.fract();
+    let a = at(input, base);
+    let b = at(input, base + 1);
+    a * (1.0 - t) + b * t
+}
+
+fn sample_cosine(input: &[f32], pos: f32) -> f32 {
+    let base = pos.floor() as isize;
+    let t = (1.0 - ((pos.fract() * PI).cos())) / 2.0;
+    let a = at(input, base);
+    let b = at(input, base + 1);
+    a * (1.0 - t) + b * t
+}
+
+//Catmull-Rom interpolation over the four samples surrounding `pos`.
+fn sample_cubic(input: &[f32], pos: f32) -> f32 {
+    let base = pos.floor() as isize;
+    let t = pos.fract();
+
+    let p0 = at(input, base - 1);
+    let p1 = at(input, base);
+    let p2 = at(input, base + 1);
+    let p3 = at(input, base + 2);
+
+    let a0 = -0.5 * p0 + 1.5 * p1 - 1.5 * p2 + 0.5 * p3;
+    let a1 = p0 - 2.5 * p1 + 2.0 * p2 - 0.5 * p3;
+    let a2 = -0.5 * p0 + 0.5 * p2;
+    let a3 = p1;
+
+    ((a0 * t + a1) * t + a2) * t + a3
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-7 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+//Windowed-sinc filter bank: `phases` fractional positions, each with `taps_per_phase` taps
+//centered on the ideal sample, shaped by a Hann window to bound ringing.
+fn build_polyphase_filter_bank(phases: usize, taps_per_phase: usize) -> Vec<Vec<f32>> {
+    let half = taps_per_phase as f32 / 2.0;
+
+    (0 .. phases).map(|phase| {
+        let frac = phase as f32 / phases as f32;
+        (0 .. taps_per_phase).map(|n| {
+            let x = n as f32 - half + frac;
+            let hann = 0.5 * (1.0 - (2.0 * PI * n as f32 / (taps_per_phase as f32 - 1.0)).cos());
+            sinc(x) * hann
+        }).collect()
+    }).collect()
+}
+
+fn sample_polyphase(input: &[f32], pos: f32, filter_bank: &[Vec<f32>]) -> f32 {
+    let phases = filter_bank.len();
+    let base = pos.floor() as isize;
+    let phase = ((pos.fract() * phases as f32).floor() as usize).min(phases - 1);
+    let taps = &filter_bank[phase];
+    let half = taps.len() as isize / 2;
+
+    taps.iter().enumerate().map(|(n, &coeff)| {
+        coeff * at(input, base + half - n as isize)
+    }).sum()
+}
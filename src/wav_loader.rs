@@ -10,6 +10,10 @@ use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
 
 const CHUNK_DATA_OFFSET: usize = 8;
 
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
 #[derive(Debug)]
 struct Chunk {
     id: String,
@@ -18,6 +22,7 @@ struct Chunk {
 
 #[derive(Debug)]
 pub struct Wav {
+    pub audio_format: u16,
     pub num_channels: u16,
     pub sample_rate: u32,
     pub byte_rate: u32,
@@ -31,7 +36,10 @@ pub enum WavData {
     Unspecified,
     U8(Vec<u8>),
     I16(Vec<i16>),
+    I24(Vec<i32>),
     I32(Vec<i32>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
 }
 
 #[derive(Debug)]
@@ -89,6 +97,7 @@ impl Wav {
 
     fn new() -> Wav {
         Wav {
+            audio_format: 0,
             num_channels: 0,
             sample_rate: 0,
             byte_rate: 0,
@@ -123,40 +132,81 @@ impl Wav {
     fn parse_fmt_chunk_data(buf: &[u8], wav: &mut Wav) -> Result<(), WavLoadError> {
         let mut reader = Cursor::new(buf);
         let audio_format = try!(reader.read_u16::<LittleEndian>());
-        wav_assert!(audio_format == 1, "Audio format is not PCM".to_string());
         wav.num_channels = try!(reader.read_u16::<LittleEndian>());
         wav.sample_rate = try!(reader.read_u32::<LittleEndian>());
         wav.byte_rate = try!(reader.read_u32::<LittleEndian>());
         wav.block_align = try!(reader.read_u16::<LittleEndian>());
         wav.bits_per_sample = try!(reader.read_u16::<LittleEndian>());
+
+        wav.audio_format = match audio_format {
+            WAVE_FORMAT_PCM | WAVE_FORMAT_IEEE_FLOAT => audio_format,
+            WAVE_FORMAT_EXTENSIBLE => try!(Wav::parse_extensible_sub_format(&mut reader)),
+            _ => return Err(WavLoadError::Parse(format!("Audio format {} is not supported (expected PCM, IEEE float, or extensible)", audio_format))),
+        };
+
         Ok(())
     }
 
+    // WAVE_FORMAT_EXTENSIBLE stores the real format in the first two bytes of
+    // the SubFormat GUID, after the cbSize/validBitsPerSample/channelMask fields.
+    fn parse_extensible_sub_format(reader: &mut Cursor<&[u8]>) -> Result<u16, WavLoadError> {
+        let _cb_size = try!(reader.read_u16::<LittleEndian>());
+        let _valid_bits_per_sample = try!(reader.read_u16::<LittleEndian>());
+        let _channel_mask = try!(reader.read_u32::<LittleEndian>());
+        let sub_format = try!(reader.read_u16::<LittleEndian>());
+        wav_assert!(sub_format == WAVE_FORMAT_PCM || sub_format == WAVE_FORMAT_IEEE_FLOAT,
+            format!("Extensible sub format {} is not supported", sub_format));
+        Ok(sub_format)
+    }
+
     fn parse_data_chunk_data(buf: &[u8], wav: &mut Wav) -> Result<(), WavLoadError> {
         let mut reader = Cursor::new(buf);
-        let data_enum = match wav.bits_per_sample {
-            8 => {
+        let data_enum = match (wav.audio_format, wav.bits_per_sample) {
+            (WAVE_FORMAT_PCM, 8) => {
                 let mut data = Vec::new();
                 while let Ok(val) = reader.read_u8() {
                     data.push(val);
                 }
                 WavData::U8(data)
             },
-            16 => {
+            (WAVE_FORMAT_PCM, 16) => {
                 let mut data = Vec::new();
                 while let Ok(val) = reader.read_i16::<LittleEndian>() {
                     data.push(val);
                 }
                 WavData::I16(data)
             },
-            32 => {
+            (WAVE_FORMAT_PCM, 24) => {
+                let mut data = Vec::new();
+                let mut bytes = [0u8; 3];
+                while reader.read_exact(&mut bytes).is_ok() {
+                    let sample = (bytes[0] as i32) | ((bytes[1] as i32) << 8) | ((bytes[2] as i32) << 16);
+                    data.push((sample << 8) >> 8);
+                }
+                WavData::I24(data)
+            },
+            (WAVE_FORMAT_PCM, 32) => {
                 let mut data = Vec::new();
                 while let Ok(val) = reader.read_i32::<LittleEndian>() {
                     data.push(val);
                 }
                 WavData::I32(data)
             },
-            _ => return Err(WavLoadError::Parse(format!("Unexpected bits per sample value (got: \"{}\", expected 8, 16, or 32)", wav.bits_per_sample))),
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => {
+                let mut data = Vec::new();
+                while let Ok(val) = reader.read_f32::<LittleEndian>() {
+                    data.push(val);
+                }
+                WavData::F32(data)
+            },
+            (WAVE_FORMAT_IEEE_FLOAT, 64) => {
+                let mut data = Vec::new();
+                while let Ok(val) = reader.read_f64::<LittleEndian>() {
+                    data.push(val);
+                }
+                WavData::F64(data)
+            },
+            _ => return Err(WavLoadError::Parse(format!("Unexpected format/bits per sample combination (format: {}, bits: {})", wav.audio_format, wav.bits_per_sample))),
         };
 
         wav.data = data_enum;